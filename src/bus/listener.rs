@@ -0,0 +1,681 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context as TaskContext, Poll},
+};
+
+use anyhow::{bail, Context, Result};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+};
+use tokio_rustls::TlsAcceptor;
+use tracing::{info, warn};
+use zbus::{
+    address::transport::{Tcp, Transport, Unix, UnixSocket},
+    connection::socket::BoxedSplit,
+    Address, AuthMechanism, OwnedGuid,
+};
+
+use crate::bus::{tls, websocket::WebSocketIo};
+
+/// A single transport we're listening on, along with the (possibly resolved) address clients
+/// should use to reach it and the auth mechanism expected over it.
+pub(super) struct BoundListener {
+    pub(super) kind: ListenerKind,
+    pub(super) address: Address,
+    pub(super) auth_mechanism: AuthMechanism,
+    /// Extra files (nonce files, lock files, ...) this listener owns besides its socket, to be
+    /// removed on [`crate::bus::Bus::cleanup`].
+    pub(super) cleanup_paths: Vec<PathBuf>,
+    /// Holds the advisory lock (if any) taken on a UNIX socket's `.lock` file for as long as this
+    /// listener lives; [`unbind`] releases the `flock(2)` explicitly, and dropping the file
+    /// releases it otherwise.
+    #[allow(dead_code)]
+    pub(super) lock_file: Option<std::fs::File>,
+}
+
+impl std::fmt::Debug for BoundListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoundListener")
+            .field("address", &self.address)
+            .field("auth_mechanism", &self.auth_mechanism)
+            .finish_non_exhaustive()
+    }
+}
+
+pub(super) enum ListenerKind {
+    Unix(tokio::net::UnixListener),
+    Tcp(tokio::net::TcpListener),
+    Tls {
+        listener: tokio::net::TcpListener,
+        acceptor: TlsAcceptor,
+    },
+    NonceTcp {
+        listener: tokio::net::TcpListener,
+        nonce: Arc<[u8; 16]>,
+    },
+    WebSocket {
+        listener: tokio::net::TcpListener,
+        /// `Some` for `wss:`, `None` for plain `ws:`.
+        tls_acceptor: Option<TlsAcceptor>,
+    },
+}
+
+/// A TCP stream that may or may not have TLS wrapped around it, so the WebSocket handshake can
+/// run generically over either.
+enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<tokio_rustls::server::TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_flush(cx),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            MaybeTlsStream::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            MaybeTlsStream::Tls(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
+
+/// A connection that has been accepted but may still need further processing (e.g. a TLS
+/// handshake or a nonce check) before it's ready to be handed to [`crate::peers::Peers::add`].
+pub(super) enum Accepted {
+    Ready(BoxedSplit),
+    Tls {
+        stream: TcpStream,
+        acceptor: TlsAcceptor,
+    },
+    NonceTcp {
+        stream: TcpStream,
+        nonce: Arc<[u8; 16]>,
+    },
+    WebSocket {
+        stream: TcpStream,
+        tls_acceptor: Option<TlsAcceptor>,
+    },
+}
+
+impl Accepted {
+    /// Finish accepting the connection, performing any outstanding handshake or check.
+    ///
+    /// This is deliberately kept separate from [`BoundListener::accept`] so a slow or malicious
+    /// client can't stall the shared accept loop while this is pending; callers should run it in
+    /// the per-connection spawned task.
+    pub(super) async fn into_socket(self) -> Result<BoxedSplit> {
+        match self {
+            Accepted::Ready(socket) => Ok(socket),
+            Accepted::Tls { stream, acceptor } => {
+                let stream = acceptor.accept(stream).await?;
+
+                // zbus only has a ready-made `Socket` impl for the bare tokio stream types; for
+                // anything else (TLS, WebSocket, ...) `Tokio` adapts any `AsyncRead + AsyncWrite`.
+                Ok(zbus::connection::socket::Tokio::new(stream).into())
+            }
+            Accepted::NonceTcp { mut stream, nonce } => {
+                let mut sent = [0; 16];
+                stream.read_exact(&mut sent).await?;
+
+                if !constant_time_eq(&sent, &*nonce) {
+                    warn!("nonce-tcp client sent an invalid nonce, dropping connection.");
+                    bail!("invalid nonce-tcp nonce");
+                }
+
+                Ok(stream.into())
+            }
+            Accepted::WebSocket {
+                stream,
+                tls_acceptor,
+            } => {
+                let stream = match tls_acceptor {
+                    Some(acceptor) => MaybeTlsStream::Tls(Box::new(acceptor.accept(stream).await?)),
+                    None => MaybeTlsStream::Plain(stream),
+                };
+                let ws = tokio_tungstenite::accept_async(stream).await?;
+
+                Ok(zbus::connection::socket::Tokio::new(WebSocketIo::new(ws)).into())
+            }
+        }
+    }
+}
+
+// Not timing-attack-proof if the optimizer decides to be clever, but good enough to avoid the
+// obvious short-circuiting `==` would otherwise give a network attacker on `sent`'s first byte.
+fn constant_time_eq(a: &[u8; 16], b: &[u8; 16]) -> bool {
+    a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+impl BoundListener {
+    pub(super) async fn accept(&self) -> Result<Accepted> {
+        let accepted = match &self.kind {
+            ListenerKind::Unix(listener) => {
+                Accepted::Ready(listener.accept().await.map(|(stream, _)| stream.into())?)
+            }
+            ListenerKind::Tcp(listener) => {
+                Accepted::Ready(listener.accept().await.map(|(stream, _)| stream.into())?)
+            }
+            ListenerKind::Tls { listener, acceptor } => {
+                let (stream, _) = listener.accept().await?;
+
+                Accepted::Tls {
+                    stream,
+                    acceptor: acceptor.clone(),
+                }
+            }
+            ListenerKind::NonceTcp { listener, nonce } => {
+                let (stream, _) = listener.accept().await?;
+
+                Accepted::NonceTcp {
+                    stream,
+                    nonce: nonce.clone(),
+                }
+            }
+            ListenerKind::WebSocket {
+                listener,
+                tls_acceptor,
+            } => {
+                let (stream, _) = listener.accept().await?;
+
+                Accepted::WebSocket {
+                    stream,
+                    tls_acceptor: tls_acceptor.clone(),
+                }
+            }
+        };
+
+        Ok(accepted)
+    }
+}
+
+/// Bind a listener for each `;`-separated address in `addresses`, D-Bus config style.
+///
+/// `reuse` controls whether a stale UNIX socket file left behind by a bus that is no longer
+/// running is removed so its path can be rebound; see [`lock_unix_socket`].
+///
+/// If binding one of several addresses fails, the listeners already bound for the earlier ones
+/// are rolled back (sockets and nonce files removed, locks released) before the error is
+/// returned, rather than being dropped in place with nothing left around to `cleanup()` them.
+pub(super) async fn bind(
+    addresses: &str,
+    guid: &OwnedGuid,
+    reuse: bool,
+) -> Result<Vec<BoundListener>> {
+    let mut listeners = vec![];
+
+    for addr in addresses
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        match bind_one(addr, guid, reuse).await {
+            Result::Ok(listener) => listeners.push(listener),
+            Err(e) => {
+                for listener in &listeners {
+                    unbind(listener).await;
+                }
+
+                return Err(e);
+            }
+        }
+    }
+
+    if listeners.is_empty() {
+        bail!("No addresses to listen on.");
+    }
+
+    Ok(listeners)
+}
+
+/// Bind a single `addr` (one side of a `;`-separated address list) as described in [`bind`].
+async fn bind_one(addr: &str, guid: &OwnedGuid, reuse: bool) -> Result<BoundListener> {
+    let (kind, address, auth_mechanism, cleanup_paths, lock_file) = if let Some(params) =
+        addr.strip_prefix("tls:")
+    {
+        let (kind, address, auth_mechanism) = tls_stream(params, guid).await?;
+
+        (kind, address, auth_mechanism, vec![], None)
+    } else if let Some(params) = addr.strip_prefix("wss:") {
+        let (kind, address, auth_mechanism) = websocket_stream(params, guid, true).await?;
+
+        (kind, address, auth_mechanism, vec![], None)
+    } else if let Some(params) = addr.strip_prefix("ws:") {
+        let (kind, address, auth_mechanism) = websocket_stream(params, guid, false).await?;
+
+        (kind, address, auth_mechanism, vec![], None)
+    } else {
+        let address = Address::from_str(addr)?.set_guid(guid.clone())?;
+
+        match address.transport() {
+            Transport::Unix(unix) => {
+                // Resolve address specification into address that clients can use.
+                let addr = unix_addr(unix)?;
+                let address = Address::new(Transport::Unix(Unix::new(UnixSocket::File(
+                    addr.as_pathname()
+                        .expect("Address created for UNIX socket should always have a path.")
+                        .to_path_buf(),
+                ))))
+                .set_guid(guid.clone())?;
+
+                let (lock_file, lock_path) = match unix.path() {
+                    UnixSocket::File(path) => {
+                        let lock_file = lock_unix_socket(path, reuse)?;
+
+                        (Some(lock_file), Some(lock_path_for(path)))
+                    }
+                    _ => (None, None),
+                };
+
+                (
+                    unix_stream(addr.clone()).await?,
+                    address,
+                    AuthMechanism::External,
+                    lock_path.into_iter().collect(),
+                    lock_file,
+                )
+            }
+            Transport::Tcp(tcp) => {
+                if let Some(nonce_file) = tcp.nonce_file() {
+                    let (kind, nonce_file) = nonce_tcp_stream(tcp, nonce_file).await?;
+
+                    (
+                        kind,
+                        address.clone(),
+                        AuthMechanism::Anonymous,
+                        vec![nonce_file],
+                        None,
+                    )
+                } else {
+                    (
+                        tcp_stream(tcp).await?,
+                        address.clone(),
+                        AuthMechanism::Anonymous,
+                        vec![],
+                        None,
+                    )
+                }
+            }
+            _ => bail!("Unsupported address `{}`.", address),
+        }
+    };
+
+    Ok(BoundListener {
+        kind,
+        address,
+        auth_mechanism,
+        cleanup_paths,
+        lock_file,
+    })
+}
+
+/// Best-effort release of a listener's on-disk state: unlock its `.lock` file (if any) and remove
+/// its socket file and any other [`BoundListener::cleanup_paths`] (nonce files, ...).
+///
+/// Shared by [`crate::bus::Bus::cleanup`] (tearing down every listener of a fully-constructed
+/// `Bus`) and by [`bind`] (rolling back listeners already bound for earlier addresses when a
+/// later one in a `;`-separated list fails); failures are logged rather than propagated so that
+/// one listener's removal failing doesn't stop the others from being cleaned up too.
+pub(super) async fn unbind(listener: &BoundListener) {
+    if let Some(lock_file) = &listener.lock_file {
+        let _ = rustix::fs::flock(lock_file, rustix::fs::FlockOperation::Unlock);
+    }
+
+    if let Transport::Unix(unix) = listener.address.transport() {
+        if let UnixSocket::File(path) = unix.path() {
+            if let Err(e) = tokio::fs::remove_file(path).await {
+                warn!("Failed to remove UNIX socket `{}`: {}", path.display(), e);
+            }
+        }
+    }
+
+    for path in &listener.cleanup_paths {
+        if let Err(e) = tokio::fs::remove_file(path).await {
+            warn!("Failed to remove `{}`: {}", path.display(), e);
+        }
+    }
+}
+
+/// Path of the advisory lock file that guards a UNIX socket path, following the convention used
+/// by Rocket's UNIX listener: `/path/foo` is guarded by `/path/foo.lock`.
+fn lock_path_for(socket_path: &Path) -> PathBuf {
+    let mut lock_path = socket_path.as_os_str().to_owned();
+    lock_path.push(".lock");
+
+    PathBuf::from(lock_path)
+}
+
+/// Take an advisory, non-blocking exclusive `flock(2)` on `socket_path`'s `.lock` file to make
+/// sure no other running bus is already serving it.
+///
+/// If the lock is free, any stale socket file left over at `socket_path` by a bus that crashed or
+/// was killed is removed (when `reuse` is set) so it can be rebound; if the lock is already held,
+/// this bails out rather than stealing the socket out from under the bus serving it.
+fn lock_unix_socket(socket_path: &Path, reuse: bool) -> Result<std::fs::File> {
+    let lock_path = lock_path_for(socket_path);
+    let lock_file = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("failed to open lock file `{}`", lock_path.display()))?;
+
+    match rustix::fs::flock(
+        &lock_file,
+        rustix::fs::FlockOperation::NonBlockingLockExclusive,
+    ) {
+        Result::Ok(()) => {}
+        Err(rustix::io::Errno::WOULDBLOCK) => {
+            bail!(
+                "UNIX socket `{}` address in use by another bus (locked by `{}`).",
+                socket_path.display(),
+                lock_path.display()
+            );
+        }
+        Err(e) => return Err(e.into()),
+    }
+
+    if reuse && socket_path.exists() {
+        std::fs::remove_file(socket_path).with_context(|| {
+            format!(
+                "failed to remove stale UNIX socket `{}`",
+                socket_path.display()
+            )
+        })?;
+    }
+
+    Ok(lock_file)
+}
+
+/// Extract the `host` (default `127.0.0.1`) and required `port` parameters shared by the
+/// `tls:`, `ws:` and `wss:` address schemes; `scheme` is used only to name the address kind in
+/// error messages.
+fn host_port<'p>(params: &'p HashMap<String, String>, scheme: &str) -> Result<(&'p str, u16)> {
+    let host = params
+        .get("host")
+        .map(String::as_str)
+        .unwrap_or("127.0.0.1");
+    let port: u16 = params
+        .get("port")
+        .with_context(|| format!("`{scheme}:` address requires a `port` parameter"))?
+        .parse()
+        .context("invalid `port` parameter")?;
+
+    Ok((host, port))
+}
+
+/// Build a [`TlsAcceptor`] from the `cert`/`key` parameters shared by the `tls:` and `wss:`
+/// address schemes; `scheme` is used only to name the address kind in error messages.
+fn tls_acceptor_from_params(params: &HashMap<String, String>, scheme: &str) -> Result<TlsAcceptor> {
+    let cert = params
+        .get("cert")
+        .with_context(|| format!("`{scheme}:` address requires a `cert` parameter"))?;
+    let key = params
+        .get("key")
+        .with_context(|| format!("`{scheme}:` address requires a `key` parameter"))?;
+
+    tls::acceptor(Path::new(cert), Path::new(key))
+}
+
+/// Parse the comma-separated `key=value` parameters of a `tls:host=...,port=...,cert=...,key=...`
+/// address and bind a TLS-wrapped TCP listener for it.
+async fn tls_stream(
+    params: &str,
+    guid: &OwnedGuid,
+) -> Result<(ListenerKind, Address, AuthMechanism)> {
+    let params = parse_params(params);
+    let (host, port) = host_port(&params, "tls")?;
+
+    info!("Listening on `{host}:{port}` (TLS).");
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+    let acceptor = tls_acceptor_from_params(&params, "tls")?;
+
+    // D-Bus has no standard TLS transport, so we report the plain `tcp:` address clients would
+    // use once connected (same host and port, just wrapped in TLS on our end).
+    let address =
+        Address::from_str(&format!("tcp:host={host},port={port}"))?.set_guid(guid.clone())?;
+
+    Ok((
+        ListenerKind::Tls { listener, acceptor },
+        address,
+        AuthMechanism::Anonymous,
+    ))
+}
+
+/// Bind a `ws:host=...,port=...` (or, with `tls`, `wss:` plus `cert`/`key`) listener for
+/// browser- and proxy-friendly clients.
+async fn websocket_stream(
+    params: &str,
+    guid: &OwnedGuid,
+    tls: bool,
+) -> Result<(ListenerKind, Address, AuthMechanism)> {
+    let params = parse_params(params);
+    let scheme = if tls { "wss" } else { "ws" };
+    let (host, port) = host_port(&params, scheme)?;
+
+    info!("Listening on `{host}:{port}` ({scheme}).");
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+
+    let tls_acceptor = tls
+        .then(|| tls_acceptor_from_params(&params, scheme))
+        .transpose()?;
+
+    // Same reasoning as `tls_stream`: D-Bus has no `ws:`/`wss:` transport of its own, so we
+    // report the address as the plain TCP endpoint a (WebSocket-aware) client connects to.
+    let address =
+        Address::from_str(&format!("tcp:host={host},port={port}"))?.set_guid(guid.clone())?;
+
+    Ok((
+        ListenerKind::WebSocket {
+            listener,
+            tls_acceptor,
+        },
+        address,
+        AuthMechanism::Anonymous,
+    ))
+}
+
+fn parse_params(s: &str) -> HashMap<String, String> {
+    s.split(',')
+        .filter_map(|kv| kv.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+fn unix_addr(unix: &Unix) -> Result<std::os::unix::net::SocketAddr> {
+    use std::os::unix::net::SocketAddr;
+
+    Ok(match unix.path() {
+        #[cfg(target_os = "linux")]
+        UnixSocket::Abstract(name) => {
+            use std::os::linux::net::SocketAddrExt;
+
+            let addr = SocketAddr::from_abstract_name(name.as_encoded_bytes())?;
+            info!(
+                "Listening on abstract UNIX socket `{}`.",
+                name.to_string_lossy()
+            );
+
+            addr
+        }
+        UnixSocket::File(path) => {
+            let addr = SocketAddr::from_pathname(path)?;
+            info!(
+                "Listening on UNIX socket file `{}`.",
+                path.to_string_lossy()
+            );
+
+            addr
+        }
+        UnixSocket::Dir(dir) | UnixSocket::TmpDir(dir) => {
+            let path = dir.join(format!("dbus-{}", fastrand::u32(1_000_000..u32::MAX)));
+            let addr = SocketAddr::from_pathname(&path)?;
+            info!(
+                "Listening on UNIX socket file `{}`.",
+                path.to_string_lossy()
+            );
+
+            addr
+        }
+        _ => bail!("Unsupported address."),
+    })
+}
+
+async fn unix_stream(addr: std::os::unix::net::SocketAddr) -> Result<ListenerKind> {
+    // TODO: Use tokio::net::UnixListener directly once it supports abstract sockets:
+    //
+    // https://github.com/tokio-rs/tokio/issues/4610
+
+    let std_listener =
+        tokio::task::spawn_blocking(move || std::os::unix::net::UnixListener::bind_addr(&addr))
+            .await??;
+    std_listener.set_nonblocking(true)?;
+    tokio::net::UnixListener::from_std(std_listener)
+        .map(ListenerKind::Unix)
+        .map_err(Into::into)
+}
+
+async fn tcp_stream(tcp: &Tcp) -> Result<ListenerKind> {
+    info!("Listening on `{}:{}`.", tcp.host(), tcp.port());
+    let address = (tcp.host(), tcp.port());
+
+    tokio::net::TcpListener::bind(address)
+        .await
+        .map(ListenerKind::Tcp)
+        .map_err(Into::into)
+}
+
+/// Bind a `nonce-tcp` listener: generate a 16-byte nonce, write it to `nonce_file` (owner-only
+/// readable, as clients must read it out-of-band to prove they have local access to it), and
+/// require each connecting client to send it back as the first 16 bytes on the wire.
+async fn nonce_tcp_stream(tcp: &Tcp, nonce_file: &Path) -> Result<(ListenerKind, PathBuf)> {
+    info!("Listening on `{}:{}` (nonce-tcp).", tcp.host(), tcp.port());
+    let listener = tokio::net::TcpListener::bind((tcp.host(), tcp.port())).await?;
+
+    // `fastrand` is a fast but non-cryptographic PRNG; nonce-tcp's auth model depends on the
+    // nonce being unguessable to anyone without local access to `nonce_file`, so it's generated
+    // from an OS-backed CSPRNG instead.
+    let mut nonce = [0u8; 16];
+    getrandom::getrandom(&mut nonce).context("failed to generate nonce-tcp nonce")?;
+
+    // Open with owner-only permissions from the start (rather than `write` then `chmod`), so
+    // there's no window where another local user could read the nonce before it's locked down.
+    #[cfg(unix)]
+    let mut file = {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(nonce_file)
+            .await
+            .with_context(|| format!("failed to create nonce file `{}`", nonce_file.display()))?
+    };
+    #[cfg(not(unix))]
+    let mut file = tokio::fs::File::create(nonce_file)
+        .await
+        .with_context(|| format!("failed to create nonce file `{}`", nonce_file.display()))?;
+
+    file.write_all(&nonce).await?;
+
+    Ok((
+        ListenerKind::NonceTcp {
+            listener,
+            nonce: Arc::new(nonce),
+        },
+        nonce_file.to_path_buf(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constant_time_eq_equal() {
+        assert!(constant_time_eq(&[1; 16], &[1; 16]));
+    }
+
+    #[test]
+    fn constant_time_eq_all_zero() {
+        assert!(constant_time_eq(&[0; 16], &[0; 16]));
+    }
+
+    #[test]
+    fn constant_time_eq_unequal() {
+        let mut a = [0u8; 16];
+        let mut b = [0u8; 16];
+        a[0] = 1;
+        assert!(!constant_time_eq(&a, &b));
+
+        b[15] = 1;
+        assert!(!constant_time_eq(&a, &b));
+    }
+
+    #[test]
+    fn lock_path_for_appends_lock_suffix() {
+        assert_eq!(
+            lock_path_for(Path::new("/run/user/1000/bus")),
+            Path::new("/run/user/1000/bus.lock"),
+        );
+    }
+
+    #[test]
+    fn parse_params_splits_key_value_pairs() {
+        let params = parse_params("host=localhost,port=1234,cert=/tmp/cert.pem");
+
+        assert_eq!(params.get("host").map(String::as_str), Some("localhost"));
+        assert_eq!(params.get("port").map(String::as_str), Some("1234"));
+        assert_eq!(
+            params.get("cert").map(String::as_str),
+            Some("/tmp/cert.pem")
+        );
+    }
+
+    #[test]
+    fn parse_params_ignores_malformed_pairs() {
+        let params = parse_params("host=localhost,garbage,port=1234");
+
+        assert_eq!(params.len(), 2);
+        assert!(!params.contains_key("garbage"));
+    }
+
+    #[test]
+    fn parse_params_empty_string() {
+        assert!(parse_params("").is_empty());
+    }
+}
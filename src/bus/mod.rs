@@ -1,79 +1,73 @@
-use anyhow::{bail, Ok, Result};
-use std::{env, path::Path, str::FromStr, sync::Arc};
-use tokio::{fs::remove_file, spawn};
-use tracing::{debug, info, trace, warn};
-use zbus::{
-    address::{
-        transport::{Tcp, Unix, UnixSocket},
-        Transport,
-    },
-    connection::{self, socket::BoxedSplit},
-    Address, AuthMechanism, Connection, Guid, OwnedGuid,
+use anyhow::{Ok, Result};
+use futures_util::future::select_all;
+use std::{
+    env,
+    future::Future,
+    path::Path,
+    str::FromStr,
+    sync::{atomic::AtomicUsize, atomic::Ordering, Arc},
 };
+use tokio::spawn;
+use tracing::{debug, info, trace, warn};
+use zbus::{connection, Address, AuthMechanism, Connection, Guid, OwnedGuid};
 
 use crate::{
     fdo::{self, DBus, Monitoring},
     peers::Peers,
 };
 
+mod listener;
+mod tls;
+mod websocket;
+use listener::{Accepted, BoundListener};
+
+/// How long [`Bus::accept`] waits before retrying a listener slot that just failed to accept, so
+/// a listener stuck permanently erroring can't spin the accept loop.
+const ACCEPT_ERROR_BACKOFF: std::time::Duration = std::time::Duration::from_millis(50);
+
 /// The bus.
 #[derive(Debug)]
 pub struct Bus {
     inner: Inner,
-    listener: Listener,
+    listeners: Vec<BoundListener>,
 }
 
 // All (cheaply) cloneable fields of `Bus` go here.
 #[derive(Clone, Debug)]
 pub struct Inner {
-    address: Address,
     peers: Arc<Peers>,
     guid: OwnedGuid,
-    next_id: usize,
-    auth_mechanism: AuthMechanism,
+    next_id: Arc<AtomicUsize>,
     _self_conn: Connection,
 }
 
-#[derive(Debug)]
-enum Listener {
-    Unix(tokio::net::UnixListener),
-    Tcp(tokio::net::TcpListener),
-}
-
 impl Bus {
-    pub async fn for_address(address: Option<&str>) -> Result<Self> {
-        let mut address = match address {
-            Some(address) => Address::from_str(address)?,
-            None => Address::from_str(&default_address())?,
+    /// Create a bus listening on `address` (or the platform default if `None`).
+    ///
+    /// `reuse` controls whether a stale UNIX socket file left behind by a bus that's no longer
+    /// running is removed so its path can be rebound; callers should normally pass `true`.
+    pub async fn for_address(address: Option<&str>, reuse: bool) -> Result<Self> {
+        let addresses = match address {
+            Some(address) => address.to_string(),
+            None => default_address(),
         };
-        let guid: OwnedGuid = match address.guid() {
-            Some(guid) => guid.to_owned().into(),
-            None => {
-                let guid = Guid::generate();
-                address = address.set_guid(guid.clone())?;
 
-                guid.into()
-            }
-        };
-        let (listener, auth_mechanism) = match address.transport() {
-            Transport::Unix(unix) => {
-                // Resolve address specification into address that clients can use.
-                let addr = Self::unix_addr(unix)?;
-                address = Address::new(Transport::Unix(Unix::new(UnixSocket::File(
-                    addr.as_pathname()
-                        .expect("Address created for UNIX socket should always have a path.")
-                        .to_path_buf(),
-                ))))
-                .set_guid(guid.clone())?;
-
-                (
-                    Self::unix_stream(addr.clone()).await?,
-                    AuthMechanism::External,
-                )
-            }
-            Transport::Tcp(tcp) => (Self::tcp_stream(tcp).await?, AuthMechanism::Anonymous),
-            _ => bail!("Unsupported address `{}`.", address),
-        };
+        // A GUID may be specified on (at most) one of the `;`-separated addresses; otherwise we
+        // generate one and use it for all of them, same as a real bus would for all its listening
+        // sockets.
+        let guid: OwnedGuid = addresses
+            .split(';')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .find_map(|addr| {
+                Address::from_str(addr)
+                    .ok()?
+                    .guid()
+                    .map(|guid| guid.to_owned().into())
+            })
+            .unwrap_or_else(|| Guid::generate().into());
+
+        let listeners = listener::bind(&addresses, &guid, reuse).await?;
 
         let peers = Peers::new();
 
@@ -100,115 +94,92 @@ impl Bus {
         trace!("Self-dial connection created.");
 
         Ok(Self {
-            listener,
+            listeners,
             inner: Inner {
-                address,
                 peers,
                 guid,
-                next_id: 0,
-                auth_mechanism,
+                next_id: Arc::new(AtomicUsize::new(0)),
                 _self_conn: service_conn,
             },
         })
     }
 
+    /// The address of the first listener.
+    ///
+    /// Use [`Bus::addresses`] to enumerate all of them.
     pub fn address(&self) -> &Address {
-        &self.inner.address
+        &self.listeners[0].address
     }
 
-    pub async fn run(&mut self) -> Result<()> {
-        loop {
-            self.accept_next().await?;
-        }
+    /// The addresses of all the listeners the bus is accepting connections on.
+    pub fn addresses(&self) -> impl Iterator<Item = &Address> {
+        self.listeners.iter().map(|listener| &listener.address)
     }
 
-    // AsyncDrop would have been nice!
-    pub async fn cleanup(self) -> Result<()> {
-        match self.inner.address.transport() {
-            Transport::Unix(unix) => match unix.path() {
-                UnixSocket::File(path) => remove_file(path).await.map_err(Into::into),
-                _ => Ok(()),
-            },
-            _ => Ok(()),
-        }
+    /// Accept connections forever, until killed.
+    pub async fn run(self) -> Result<()> {
+        self.run_until(std::future::pending()).await
     }
 
-    fn unix_addr(unix: &Unix) -> Result<std::os::unix::net::SocketAddr> {
-        use std::os::unix::net::SocketAddr;
-
-        Ok(match unix.path() {
-            #[cfg(target_os = "linux")]
-            UnixSocket::Abstract(name) => {
-                use std::os::linux::net::SocketAddrExt;
-
-                let addr = SocketAddr::from_abstract_name(name.as_encoded_bytes())?;
-                info!(
-                    "Listening on abstract UNIX socket `{}`.",
-                    name.to_string_lossy()
-                );
+    /// Accept connections until `shutdown` resolves, then stop accepting, close all peer
+    /// connections and [`Bus::cleanup`] after itself, so the bus can be embedded in a larger
+    /// service and torn down deterministically rather than only killed by process exit.
+    ///
+    /// A connection that merely fails to be accepted (e.g. a transient `EMFILE` from the OS) is
+    /// logged and does not stop this from running to completion; only `shutdown` resolving does.
+    pub async fn run_until(self, shutdown: impl Future<Output = ()>) -> Result<()> {
+        tokio::pin!(shutdown);
 
-                addr
-            }
-            UnixSocket::File(path) => {
-                let addr = SocketAddr::from_pathname(path)?;
-                info!(
-                    "Listening on UNIX socket file `{}`.",
-                    path.to_string_lossy()
-                );
-
-                addr
-            }
-            UnixSocket::Dir(dir) | UnixSocket::TmpDir(dir) => {
-                let path = dir.join(format!("dbus-{}", fastrand::u32(1_000_000..u32::MAX)));
-                let addr = SocketAddr::from_pathname(&path)?;
-                info!(
-                    "Listening on UNIX socket file `{}`.",
-                    path.to_string_lossy()
-                );
-
-                addr
+        loop {
+            tokio::select! {
+                result = self.accept_next() => {
+                    if let Err(e) = result {
+                        warn!("Failed to accept a connection: {}; still accepting.", e);
+                    }
+                }
+                () = &mut shutdown => break,
             }
-            _ => bail!("Unsupported address."),
-        })
-    }
+        }
+
+        info!("Shutting down, closing all peer connections.");
+        self.inner.peers.disconnect_all().await;
 
-    async fn unix_stream(addr: std::os::unix::net::SocketAddr) -> Result<Listener> {
-        // TODO: Use tokio::net::UnixListener directly once it supports abstract sockets:
-        //
-        // https://github.com/tokio-rs/tokio/issues/4610
-
-        let std_listener =
-            tokio::task::spawn_blocking(move || std::os::unix::net::UnixListener::bind_addr(&addr))
-                .await??;
-        std_listener.set_nonblocking(true)?;
-        tokio::net::UnixListener::from_std(std_listener)
-            .map(Listener::Unix)
-            .map_err(Into::into)
+        self.cleanup().await
     }
 
-    async fn tcp_stream(tcp: &Tcp) -> Result<Listener> {
-        if tcp.nonce_file().is_some() {
-            bail!("`nonce-tcp` transport is not supported (yet).");
+    // AsyncDrop would have been nice!
+    //
+    // Best-effort per listener (see `listener::unbind`): one listener's socket/lock/nonce file
+    // failing to be removed must not skip cleaning up every listener after it.
+    pub async fn cleanup(self) -> Result<()> {
+        for listener in &self.listeners {
+            listener::unbind(listener).await;
         }
-        info!("Listening on `{}:{}`.", tcp.host(), tcp.port());
-        let address = (tcp.host(), tcp.port());
 
-        tokio::net::TcpListener::bind(address)
-            .await
-            .map(Listener::Tcp)
-            .map_err(Into::into)
+        Ok(())
     }
 
-    async fn accept_next(&mut self) -> Result<()> {
-        let socket = self.accept().await?;
+    async fn accept_next(&self) -> Result<()> {
+        let (accepted, auth_mechanism) = self.accept().await?;
 
         let id = self.next_id();
         let inner = self.inner.clone();
         spawn(async move {
+            // Any remaining handshake (e.g TLS) happens here rather than in the shared accept
+            // loop, so a slow or malicious client can only ever stall its own connection.
+            let socket = match accepted.into_socket().await {
+                Result::Ok(socket) => socket,
+                Err(e) => {
+                    warn!("Failed to establish connection: {}", e);
+
+                    return;
+                }
+            };
+
             if let Err(e) = inner
                 .peers
                 .clone()
-                .add(&inner.guid, id, socket, inner.auth_mechanism)
+                .add(&inner.guid, id, socket, auth_mechanism)
                 .await
             {
                 warn!("Failed to establish connection: {}", e);
@@ -218,14 +189,55 @@ impl Bus {
         Ok(())
     }
 
-    async fn accept(&mut self) -> Result<BoxedSplit> {
-        let stream = match &mut self.listener {
-            Listener::Unix(listener) => listener.accept().await.map(|(stream, _)| stream.into())?,
-            Listener::Tcp(listener) => listener.accept().await.map(|(stream, _)| stream.into())?,
-        };
-        debug!("Accepted connection on address `{}`", self.inner.address);
+    /// Wait for the next connection on any of [`Self::listeners`].
+    ///
+    /// A listener whose `accept()` fails (e.g. a transient `EMFILE`/`ECONNABORTED`) doesn't take
+    /// the others down with it: the failure is logged and that listener's slot in the shared
+    /// `select_all` is simply replaced with a fresh `accept()` call, same as a real bus serving
+    /// multiple addresses wouldn't let one bad listener stop it serving the rest.
+    async fn accept(&self) -> Result<(Accepted, AuthMechanism)> {
+        let mut accepts: Vec<_> = self
+            .listeners
+            .iter()
+            .enumerate()
+            .map(|(idx, listener)| Box::pin(accept_one(idx, listener)))
+            .collect();
 
-        Ok(stream)
+        loop {
+            // `select_all` hands back the remaining futures reordered (it `swap_remove`s the
+            // ready one), so each future carries its own listener's index instead.
+            let ((ready_idx, result, auth_mechanism), _, mut remaining) =
+                select_all(accepts).await;
+
+            match result {
+                Result::Ok(accepted) => {
+                    debug!(
+                        "Accepted connection on address `{}`",
+                        self.listeners[ready_idx].address
+                    );
+
+                    return Ok((accepted, auth_mechanism));
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to accept a connection on `{}`: {}; still accepting on the other listeners.",
+                        self.listeners[ready_idx].address, e
+                    );
+
+                    // If this listener is permanently ready-with-error (e.g. its fd was closed
+                    // out from under it), retrying it bare would spin this task and starve
+                    // `run_until`'s `select!` of ever polling `shutdown` again; so back off a
+                    // little before re-arming its slot.
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+
+                    remaining.push(Box::pin(accept_one(
+                        ready_idx,
+                        &self.listeners[ready_idx],
+                    )));
+                    accepts = remaining;
+                }
+            }
+        }
     }
 
     pub fn peers(&self) -> &Arc<Peers> {
@@ -236,17 +248,23 @@ impl Bus {
         &self.inner.guid
     }
 
+    /// The authentication mechanism expected on the first listener.
     pub fn auth_mechanism(&self) -> AuthMechanism {
-        self.inner.auth_mechanism
+        self.listeners[0].auth_mechanism
     }
 
-    fn next_id(&mut self) -> usize {
-        self.inner.next_id += 1;
-
-        self.inner.next_id
+    fn next_id(&self) -> usize {
+        self.inner.next_id.fetch_add(1, Ordering::Relaxed) + 1
     }
 }
 
+async fn accept_one(
+    idx: usize,
+    listener: &BoundListener,
+) -> (usize, Result<Accepted>, AuthMechanism) {
+    (idx, listener.accept().await, listener.auth_mechanism)
+}
+
 fn default_address() -> String {
     let runtime_dir = env::var("XDG_RUNTIME_DIR")
         .as_ref()
@@ -0,0 +1,31 @@
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use anyhow::{Context, Result};
+use tokio_rustls::{
+    rustls::{pki_types::PrivateKeyDer, ServerConfig},
+    TlsAcceptor,
+};
+
+/// Build a TLS acceptor from a PEM-encoded certificate chain and private key.
+pub(super) fn acceptor(cert_path: &Path, key_path: &Path) -> Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(
+        File::open(cert_path)
+            .with_context(|| format!("failed to open TLS certificate `{}`", cert_path.display()))?,
+    ))
+    .collect::<Result<Vec<_>, _>>()
+    .with_context(|| format!("failed to parse TLS certificate `{}`", cert_path.display()))?;
+
+    let key: PrivateKeyDer<'static> = rustls_pemfile::private_key(&mut BufReader::new(
+        File::open(key_path)
+            .with_context(|| format!("failed to open TLS private key `{}`", key_path.display()))?,
+    ))
+    .with_context(|| format!("failed to parse TLS private key `{}`", key_path.display()))?
+    .with_context(|| format!("no private key found in `{}`", key_path.display()))?;
+
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("invalid TLS certificate/key pair")?;
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
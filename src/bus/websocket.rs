@@ -0,0 +1,93 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio_tungstenite::{tungstenite::Message, WebSocketStream};
+
+/// Adapts a [`WebSocketStream`] framed in binary messages into the continuous byte stream the
+/// SASL and D-Bus message layers expect, buffering whatever's left of a message across reads.
+pub(super) struct WebSocketIo<S> {
+    inner: WebSocketStream<S>,
+    pending: Vec<u8>,
+    pending_pos: usize,
+}
+
+impl<S> WebSocketIo<S> {
+    pub(super) fn new(inner: WebSocketStream<S>) -> Self {
+        Self {
+            inner,
+            pending: Vec::new(),
+            pending_pos: 0,
+        }
+    }
+}
+
+impl<S> AsyncRead for WebSocketIo<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            if self.pending_pos < self.pending.len() {
+                let rest = &self.pending[self.pending_pos..];
+                let n = rest.len().min(buf.remaining());
+                buf.put_slice(&rest[..n]);
+                self.pending_pos += n;
+
+                return Poll::Ready(Ok(()));
+            }
+
+            match ready!(self.inner.poll_next_unpin(cx)) {
+                Some(Ok(Message::Binary(data))) => {
+                    self.pending = data;
+                    self.pending_pos = 0;
+
+                    continue;
+                }
+                // D-Bus only ever speaks binary over the wire; anything else is framing noise
+                // tungstenite itself doesn't swallow (pings/pongs/close are handled by it).
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, e))),
+                None => return Poll::Ready(Ok(())),
+            }
+        }
+    }
+}
+
+impl<S> AsyncWrite for WebSocketIo<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.inner.poll_ready_unpin(cx)).map_err(to_io_error)?;
+        self.inner
+            .start_send_unpin(Message::Binary(buf.to_vec()))
+            .map_err(to_io_error)?;
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_flush_unpin(cx).map_err(to_io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_close_unpin(cx).map_err(to_io_error)
+    }
+}
+
+fn to_io_error(e: tokio_tungstenite::tungstenite::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}